@@ -1,14 +1,46 @@
-use std::{boxed::Box, fmt};
+use std::{boxed::Box, collections::HashMap, fmt, marker::PhantomData};
 
 use serde::{
-    de::DeserializeOwned,
+    de::{Error as _, DeserializeOwned},
     Serialize,
 };
-use serde_json::{Value, value::RawValue};
+use serde_json::value::RawValue;
 use thiserror::Error;
 
 use crate::jsonrpc::{JsonRpcError, Request, Response};
 
+/// A lightweight handle to a request previously inserted into a `BatchRequest`, returned by
+/// [`BatchRequest::add_request`].
+///
+/// Use it to retrieve the matching, typed response from the `BatchResponse` the batch eventually
+/// produces, via [`BatchResponse::get`], regardless of the order responses came back in.
+pub struct BatchHandle<T> {
+    index: usize,
+    _result: PhantomData<fn() -> T>,
+}
+
+impl<T> BatchHandle<T> {
+    fn new(index: usize) -> Self {
+        Self { index, _result: PhantomData }
+    }
+}
+
+// Implemented manually: `#[derive(Clone, Copy)]` would require `T: Clone`/`T: Copy`, but a
+// handle does not actually own a `T`.
+impl<T> Clone for BatchHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BatchHandle<T> {}
+
+impl<T> fmt::Debug for BatchHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchHandle").field("index", &self.index).finish()
+    }
+}
+
 /// Error thrown when handling batches of JSON-RPC request and responses.
 #[derive(Error, Debug)]
 pub enum BatchError {
@@ -34,19 +66,29 @@ impl std::fmt::Display for BatchError {
 }
 
 /// A batch of JSON-RPC requests.
+///
+/// Each request is stored pre-serialized, as a `Box<RawValue>`, instead of a parsed
+/// `serde_json::Value` tree. This avoids a `to_value`/re-serialize round trip both when the
+/// request is added and when the batch is later sent: the relay writes these raw bytes straight
+/// through, and [`set_ids`](Self::set_ids) patches the `id` field in place rather than rebuilding
+/// the whole tree.
 #[derive(Clone, Debug, Default)]
 pub struct BatchRequest {
-    requests: Vec<Value>,
+    requests: Vec<Box<RawValue>>,
+    // The id assigned to the first request in the batch by `set_ids`, carried through to the
+    // `BatchResponse` this batch produces so `BatchHandle`s can be resolved even if the relay
+    // omits a response.
+    base_id: Option<u64>,
 }
 
 impl BatchRequest {
     pub fn new() -> Self {
-        Self { requests: Vec::new() }
+        Self { requests: Vec::new(), base_id: None }
     }
 
 
     pub fn with_capacity(capacity: usize) -> Self {
-        Self { requests: Vec::with_capacity(capacity) }
+        Self { requests: Vec::with_capacity(capacity), base_id: None }
     }
 
     /// Returns the number of requests in the batch.
@@ -60,42 +102,55 @@ impl BatchRequest {
     }
 
 
-    pub fn add_request<T>(&mut self, method: &str, params: T) -> Result<(), BatchError>
+    /// Adds a request to the batch and returns a [`BatchHandle`] that can later be used to
+    /// retrieve its response from the `BatchResponse` this batch produces.
+    pub fn add_request<T, R>(&mut self, method: &str, params: T) -> Result<BatchHandle<R>, BatchError>
         where
             T: Serialize,
     {
-        self.requests.push(serde_json::to_value(&Request::new(0, method, params))?);
+        let index = self.requests.len();
+        let request = RawValue::from_string(serde_json::to_string(&Request::new(0, method, params))?)?;
+        self.requests.push(request);
 
-        Ok(())
+        Ok(BatchHandle::new(index))
     }
 
     /// Sets the ids of the requests.
     ///
+    /// Rewrites the `"id":<n>` field of each request in place, rather than parsing the request
+    /// back into a `Value` tree just to overwrite one field.
+    ///
     /// # Arguments
     ///
     /// `first` - id for the first request in the batch.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// If one of the requests is malformed.
-    pub(crate) fn set_ids(&mut self, mut first: u64) -> Result<(), BatchError> {
+    /// Returns `BatchError::JsonError` if one of the requests is malformed.
+    pub(crate) fn set_ids(&mut self, first: u64) -> Result<(), BatchError> {
+        let mut id = first;
         let requests = self.requests_mut()?;
         for request in requests {
-            *request
-                .get_mut("id")
-                .expect("Malformed JSON-RPC request: {request}, id is missing.") = first.into();
-            first += 1;
+            *request = set_id(request, id)?;
+            id += 1;
         }
 
+        self.base_id = Some(first);
         Ok(())
     }
 
+    /// Returns the id assigned to the first request in the batch by [`set_ids`](Self::set_ids),
+    /// or `None` if `set_ids` has not been called yet.
+    pub(crate) fn base_id(&self) -> Option<u64> {
+        self.base_id
+    }
+
     /// Returns a mutable reference to the underlying JSON-RPC requests.
     ///
     /// # Errors
     ///
     /// Returns `BatchError::EmptyBatch` if the batch is empty.
-    pub(crate) fn requests_mut(&mut self) -> Result<&mut [Value], BatchError> {
+    pub(crate) fn requests_mut(&mut self) -> Result<&mut [Box<RawValue>], BatchError> {
         (!self.is_empty()).then(move || &mut self.requests[..]).ok_or(BatchError::EmptyBatch)
     }
 
@@ -104,48 +159,101 @@ impl BatchRequest {
     /// # Errors
     ///
     /// Returns `BatchError::EmptyBatch` if the batch is empty.
-    pub(crate) fn requests(&self) -> Result<&[Value], BatchError> {
+    pub(crate) fn requests(&self) -> Result<&[Box<RawValue>], BatchError> {
         (!self.is_empty()).then(|| &self.requests[..]).ok_or(BatchError::EmptyBatch)
     }
 }
 
+/// Rewrites the `"id":<n>` field of a serialized request to `id`, without otherwise touching the
+/// rest of the payload.
+///
+/// `Request` always serializes `id` as its first field, so this only needs to scan for the
+/// `"id":` key and the digits following it, rather than re-parsing the whole object.
+fn set_id(request: &RawValue, id: u64) -> Result<Box<RawValue>, BatchError> {
+    const KEY: &str = "\"id\":";
+
+    let text = request.get();
+    let value_start = text.find(KEY).ok_or_else(|| malformed_request(text))?.checked_add(KEY.len()).unwrap();
+    let value_len =
+        text[value_start..].find(|c: char| !c.is_ascii_digit()).ok_or_else(|| malformed_request(text))?;
+
+    let mut patched = String::with_capacity(text.len());
+    patched.push_str(&text[..value_start]);
+    patched.push_str(&id.to_string());
+    patched.push_str(&text[value_start + value_len..]);
+
+    Ok(RawValue::from_string(patched)?)
+}
+
+fn malformed_request(text: &str) -> BatchError {
+    BatchError::JsonError(serde_json::Error::custom(format!(
+        "Malformed JSON-RPC request: {text}, id is missing."
+    )))
+}
+
 /// A batch of JSON-RPC responses.
+///
+/// The raw response body is kept around, and each response's `result`/`error` is recorded as
+/// either a byte range into that body or an owned `JsonRpcError`, rather than eagerly
+/// deserializing every result into its own `Box<RawValue>`. The payload behind a given id is only
+/// ever sliced out - and only actually deserialized - once [`next_response`](Self::next_response)
+/// or [`get`](Self::get) is called for it.
+///
+/// Responses are stored in an id-keyed map, so they can be retrieved in any order: either by
+/// popping them off in ascending id order with [`next_response`](Self::next_response), or by
+/// looking one up directly with the [`BatchHandle`] returned when its request was inserted into
+/// the originating `BatchRequest`, via [`get`](Self::get).
 #[derive(Clone, Debug)]
 pub struct BatchResponse {
-    responses: Vec<(u64, Result<Box<RawValue>, JsonRpcError>)>,
+    text: String,
+    responses: HashMap<u64, Result<(usize, usize), JsonRpcError>>,
+    // The id assigned to the first request in the batch, as carried over from
+    // `BatchRequest::base_id`, rather than derived from whichever responses happen to be present.
+    // Combined with a `BatchHandle`'s index, this gives back the id of the response it refers to,
+    // even if the relay omits the response for the lowest id in the batch.
+    base_id: Option<u64>,
 }
 
 impl BatchResponse {
-    /// Creates a new batch of JSON-RPC responses.
+    /// Parses `text`, the raw JSON-RPC batch response body, without deserializing the individual
+    /// results it contains.
     ///
     /// # Arguments
     ///
-    /// `responses` - vector of JSON-RPC responses.
-    pub(crate) fn new(responses: Vec<Response>) -> Self {
-        let mut responses = responses
-            .into_iter()
-            .map(|response| match response {
-                Response::Success { id, result } => (id, Ok(result.to_owned())),
-                Response::Error { id, error } => (id, Err(error)),
-                _ => unreachable!(),
-            })
-            .collect::<Vec<(u64, Result<Box<RawValue>, JsonRpcError>)>>();
-        // Sort the responses by descending id, as the order the requests were issued and the order
-        // the responses were given may differ. Order is reversed because we pop elements when
-        // retrieving the responses.
-        responses.sort_by_key(|(id, _)| std::cmp::Reverse(*id));
+    /// `text` - the response body returned by the relay for a batch request.
+    ///
+    /// `base_id` - the id assigned to the first request in the originating batch, i.e.
+    /// `batch.base_id()` after `set_ids` was called on it.
+    pub(crate) fn new(text: String, base_id: Option<u64>) -> Result<Self, BatchError> {
+        let base_ptr = text.as_ptr() as usize;
 
-        Self { responses }
-    }
+        let mut responses = HashMap::new();
+        for response in serde_json::from_str::<Vec<Response<'_>>>(&text)? {
+            let (id, body) = match response {
+                Response::Success { id, result } => {
+                    // `result` borrows directly from `text`, so its offset within `text` can be
+                    // recovered from the two pointers, with no further parsing.
+                    let raw = result.get();
+                    let start = raw.as_ptr() as usize - base_ptr;
+                    (id, Ok((start, start + raw.len())))
+                }
+                Response::Error { id, error } => (id, Err(error)),
+                Response::Notification { .. } => {
+                    return Err(BatchError::JsonRpcError(JsonRpcError {
+                        code: -32600,
+                        message: "a batch response cannot contain a notification".to_string(),
+                        data: None,
+                    }))
+                }
+            };
+            responses.insert(id, body);
+        }
 
-    /// Returns the id of the batch, that is the id of the first response.
-    pub(crate) fn id(&self) -> Result<u64, BatchError> {
-        // The id of the first request in the batch, be it successful or not, corresponds to the
-        // id of the channel to send the response into.
-        self.responses.last().map(|(id, _)| *id).ok_or(BatchError::EmptyBatch)
+        Ok(Self { text, responses, base_id })
     }
 
-    /// Returns the next response in the batch or `None` if the batch is empty.
+    /// Returns the next response in the batch, in ascending id order, or `None` if the batch is
+    /// empty.
     ///
     /// # Errors
     ///
@@ -154,12 +262,45 @@ impl BatchResponse {
         where
             T: DeserializeOwned,
     {
-        // The order is reversed.
-        let item = self.responses.pop();
-        // Deserializes and returns the response.
-        item.map(|(_, body)| {
-            body.map_err(Into::into)
-                .and_then(|res| serde_json::from_str::<T>(res.get()).map_err(Into::into))
+        let id = *self.responses.keys().min()?;
+        let body = self.responses.remove(&id).expect("key was just read from this map");
+        Some(self.deserialize(body))
+    }
+
+    /// Returns the response matching `handle`, deserialized as `T`, or `None` if that response is
+    /// not (or no longer) present in this batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error corresponding to the underlying JSON-RPC request if it failed.
+    pub fn get<T>(&self, handle: &BatchHandle<T>) -> Option<Result<T, BatchError>>
+        where
+            T: DeserializeOwned,
+    {
+        let id = self.base_id? + handle.index as u64;
+        let body = self.responses.get(&id)?.clone();
+        Some(self.deserialize(body))
+    }
+
+    /// Returns the response matching `handle`, without deserializing its result, or `None` if
+    /// that response is not (or no longer) present in this batch.
+    pub(crate) fn get_raw<T>(&self, handle: &BatchHandle<T>) -> Option<Result<Box<RawValue>, JsonRpcError>> {
+        let id = self.base_id? + handle.index as u64;
+        let body = self.responses.get(&id)?.clone();
+        Some(self.to_raw(body))
+    }
+
+    /// Deserializes `body`'s byte range out of `self.text` as `T`.
+    fn deserialize<T: DeserializeOwned>(&self, body: Result<(usize, usize), JsonRpcError>) -> Result<T, BatchError> {
+        body.map_err(Into::into)
+            .and_then(|(start, end)| serde_json::from_str::<T>(&self.text[start..end]).map_err(Into::into))
+    }
+
+    /// Copies `body`'s byte range out of `self.text` into an owned `RawValue`.
+    fn to_raw(&self, body: Result<(usize, usize), JsonRpcError>) -> Result<Box<RawValue>, JsonRpcError> {
+        body.map(|(start, end)| {
+            RawValue::from_string(self.text[start..end].to_owned())
+                .expect("a previously-parsed result is valid JSON")
         })
     }
 
@@ -172,4 +313,4 @@ impl BatchResponse {
     pub fn is_empty(&self) -> bool {
         self.responses.is_empty()
     }
-}
\ No newline at end of file
+}
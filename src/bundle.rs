@@ -0,0 +1,71 @@
+use ethers::types::{Bytes, H256, U256, U64};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Parameters for an `eth_sendBundle`/`eth_callBundle` request, as expected by Flashbots-style
+/// relays.
+#[derive(Clone, Debug, Serialize)]
+pub struct BundleRequest {
+    txs: Vec<Bytes>,
+    #[serde(rename = "blockNumber")]
+    block_number: U64,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "minTimestamp")]
+    min_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "maxTimestamp")]
+    max_timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", rename = "revertingTxHashes")]
+    reverting_tx_hashes: Option<Vec<H256>>,
+}
+
+impl BundleRequest {
+    /// Creates a new bundle of signed, RLP-encoded transactions targeting `block_number`.
+    pub fn new(txs: Vec<Bytes>, block_number: U64) -> Self {
+        Self { txs, block_number, min_timestamp: None, max_timestamp: None, reverting_tx_hashes: None }
+    }
+
+    /// Sets the earliest unix timestamp at which the bundle is valid.
+    pub fn min_timestamp(mut self, min_timestamp: u64) -> Self {
+        self.min_timestamp = Some(min_timestamp);
+        self
+    }
+
+    /// Sets the latest unix timestamp at which the bundle is valid.
+    pub fn max_timestamp(mut self, max_timestamp: u64) -> Self {
+        self.max_timestamp = Some(max_timestamp);
+        self
+    }
+
+    /// Sets the hashes of transactions that are allowed to revert without invalidating the
+    /// bundle.
+    pub fn reverting_tx_hashes(mut self, reverting_tx_hashes: Vec<H256>) -> Self {
+        self.reverting_tx_hashes = Some(reverting_tx_hashes);
+        self
+    }
+}
+
+/// The response to an `eth_sendBundle` submission.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SendBundleResponse {
+    /// The hash of the submitted bundle.
+    #[serde(rename = "bundleHash")]
+    pub bundle_hash: H256,
+}
+
+/// The response to an `eth_callBundle` simulation.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CallBundleResponse {
+    /// The hash of the simulated bundle.
+    #[serde(rename = "bundleHash")]
+    pub bundle_hash: H256,
+    /// The effective gas price paid by the bundle as a whole.
+    #[serde(rename = "bundleGasPrice")]
+    pub bundle_gas_price: U256,
+    /// The total amount paid to the coinbase by the bundle.
+    #[serde(rename = "coinbaseDiff")]
+    pub coinbase_diff: U256,
+    /// The total gas used by the bundle.
+    #[serde(rename = "totalGasUsed")]
+    pub total_gas_used: u64,
+    /// The per-transaction simulation results, in the shape returned by the relay.
+    pub results: Vec<Value>,
+}
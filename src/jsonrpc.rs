@@ -0,0 +1,99 @@
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
+use thiserror::Error;
+
+/// A JSON-RPC 2.0 request object.
+#[derive(Serialize, Debug, Clone)]
+pub struct Request<'a, T> {
+    id: u64,
+    jsonrpc: &'a str,
+    method: &'a str,
+    params: T,
+}
+
+impl<'a, T> Request<'a, T> {
+    /// Creates a new JSON-RPC request with the given `id`, `method` and `params`.
+    pub fn new(id: u64, method: &'a str, params: T) -> Self {
+        Self { id, jsonrpc: "2.0", method, params }
+    }
+}
+
+/// A JSON-RPC 2.0 error object, as returned by a node when a request could not be handled.
+#[derive(Error, Debug, Clone, Deserialize)]
+pub struct JsonRpcError {
+    /// The error code.
+    pub code: i64,
+    /// The error message.
+    pub message: String,
+    /// Additional data provided by the error, if any.
+    pub data: Option<serde_json::Value>,
+}
+
+impl fmt::Display for JsonRpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(data) = &self.data {
+            write!(f, "(code: {}, message: {}, data: {data})", self.code, self.message)
+        } else {
+            write!(f, "(code: {}, message: {})", self.code, self.message)
+        }
+    }
+}
+
+/// A JSON-RPC 2.0 response object, as returned by a node for a single request.
+///
+/// Borrows its payload directly out of the response body via `RawValue` instead of eagerly
+/// deserializing it, so `result`/`params` stay undecoded until the caller actually asks for a
+/// concrete type.
+#[derive(Debug)]
+pub enum Response<'a> {
+    /// A successful response.
+    Success {
+        /// The id of the request this response corresponds to.
+        id: u64,
+        /// The result of the request, not yet deserialized.
+        result: &'a RawValue,
+    },
+    /// An error response.
+    Error {
+        /// The id of the request this response corresponds to.
+        id: u64,
+        /// The error returned by the node.
+        error: JsonRpcError,
+    },
+    /// A JSON-RPC notification, which is not tied to any request.
+    Notification {
+        /// The method the notification is for.
+        method: &'a str,
+        /// The notification's params, not yet deserialized.
+        params: &'a RawValue,
+    },
+}
+
+impl<'de> Deserialize<'de> for Response<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Helper<'a> {
+            id: Option<u64>,
+            #[serde(borrow)]
+            result: Option<&'a RawValue>,
+            error: Option<JsonRpcError>,
+            #[serde(borrow)]
+            method: Option<&'a str>,
+            #[serde(borrow)]
+            params: Option<&'a RawValue>,
+        }
+
+        let helper = Helper::deserialize(deserializer)?;
+        match (helper.id, helper.result, helper.error, helper.method, helper.params) {
+            (Some(id), Some(result), None, _, _) => Ok(Response::Success { id, result }),
+            (Some(id), None, Some(error), _, _) => Ok(Response::Error { id, error }),
+            (None, _, _, Some(method), Some(params)) => Ok(Response::Notification { method, params }),
+            _ => Err(serde::de::Error::custom("invalid JSON-RPC response")),
+        }
+    }
+}
@@ -2,6 +2,8 @@ mod jsonrpc;
 mod relay;
 mod middleware;
 mod batch;
+mod transport;
+mod bundle;
 
 
 #[cfg(test)]
@@ -23,8 +25,8 @@ mod tests {
         let pos = H256::from_low_u64_be(8);
 
         let mut batch = BatchRequest::with_capacity(2);
-        batch.add_request("eth_getStorageAt", (address1, pos, BlockNumber::Latest))?;
-        batch.add_request("eth_getStorageAt", (address2, pos, BlockNumber::Latest))?;
+        let handle1 = batch.add_request::<_, H256>("eth_getStorageAt", (address1, pos, BlockNumber::Latest))?;
+        let handle2 = batch.add_request::<_, H256>("eth_getStorageAt", (address2, pos, BlockNumber::Latest))?;
 
         let rpc = "https://api.avax.network/ext/bc/C/rpc";
         let http_client = Provider::<Http>::try_from(rpc)?;
@@ -34,9 +36,12 @@ mod tests {
         // let relay = relay::Relay::new(Url::parse("https://api.avax.network/ext/bc/C/rpc")?);
         // let mut http_responses = relay.execute_batch(&mut batch).await?;
 
-        let mut http_responses: BatchResponse = client.execute_batch(&mut batch).await?;
+        let http_responses: BatchResponse = client.execute_batch(&mut batch).await?;
 
-        while let Some(Ok(storage)) = http_responses.next_response::<H256>() {
+        if let Some(Ok(storage)) = http_responses.get(&handle1) {
+            println!("{storage:?}")
+        }
+        if let Some(Ok(storage)) = http_responses.get(&handle2) {
             println!("{storage:?}")
         }
 
@@ -73,7 +78,7 @@ mod tests {
             signed_tx = a + &signed_tx;
             println!("signed_tx: {:?}", signed_tx);
 
-            batch.add_request("eth_sendRawTransaction", (vec![signed_tx]))?;
+            batch.add_request::<_, H256>("eth_sendRawTransaction", (vec![signed_tx]))?;
         }
 
         let mut  http_responses = relay.execute_batch(&mut batch).await?;
@@ -1,13 +1,28 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
 use ethers::{
-    providers::{Middleware, MiddlewareError}
+    providers::{Middleware, MiddlewareError},
+    signers::LocalWallet,
 };
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{value::RawValue, Value};
 use thiserror::Error;
+use tokio::sync::{oneshot, Mutex};
 use url::Url;
 
-use crate::{
-    relay::{Relay, RelayError}
-};
-use crate::batch::{BatchRequest, BatchResponse};
+use crate::batch::{BatchError, BatchRequest, BatchResponse};
+use crate::jsonrpc::JsonRpcError;
+use crate::relay::{Relay, RelayError};
+use crate::transport::HttpTransport;
+
+/// The queue is flushed as soon as it holds this many requests, even if `DEFAULT_FLUSH_INTERVAL`
+/// has not elapsed yet.
+const DEFAULT_MAX_BATCH_SIZE: usize = 32;
+
+/// The background loop flushes the queue on every tick of this interval, on top of the flushes
+/// triggered by reaching `DEFAULT_MAX_BATCH_SIZE`.
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(10);
 
 /// Errors for the Flashbots middleware.
 #[derive(Error, Debug)]
@@ -17,6 +32,9 @@ pub enum BatchRequestMiddlewareError<M: Middleware> {
     /// The relay responded with an error.
     #[error(transparent)]
     RelayError(#[from] RelayError),
+    /// The batch containing this request was flushed, but its response never made it back.
+    #[error("the queued request was dropped before a response was received")]
+    ChannelClosed,
     /// An error occured in one of the middlewares.
     #[error("{0}")]
     MiddlewareError(M::Error),
@@ -37,6 +55,14 @@ impl<M: Middleware> MiddlewareError for BatchRequestMiddlewareError<M> {
     }
 }
 
+/// A single `request`-level call waiting to be folded into the next outgoing batch.
+#[derive(Debug)]
+struct QueuedRequest {
+    method: String,
+    params: Value,
+    sender: oneshot::Sender<Result<Box<RawValue>, JsonRpcError>>,
+}
+
 /// # Example
 /// ```
 /// use ethers::prelude::*;
@@ -60,11 +86,11 @@ impl<M: Middleware> MiddlewareError for BatchRequestMiddlewareError<M> {
 /// // middleware to sign your transactions *before* they
 /// // are sent to your Flashbots middleware.
 /// let mut client = SignerMiddleware::new(
-///     BatchRequestMiddlewareError::new(
+///     BatchRequestMiddleware::new(
 ///         provider,
 ///         Url::parse("https://relay.flashbots.net")?,
-///         signer
-///     ),
+///     )
+///     .with_signer(signer),
 ///     wallet
 /// );
 ///
@@ -74,23 +100,76 @@ impl<M: Middleware> MiddlewareError for BatchRequestMiddlewareError<M> {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// Every call made directly through [`Middleware::request`] is queued instead of being sent right
+/// away. The queue is flushed - and a single JSON-RPC batch sent to the relay - as soon as it
+/// reaches `max_batch_size`, or every `flush_interval` otherwise, whichever happens first.
+///
+/// Note that `Middleware`'s higher-level helpers, such as `get_balance`/`call`, are not coalesced:
+/// ethers' default implementations of those call straight through to `self.inner()`, not
+/// `self.request()`, so only calls that go through `request` directly are batched.
 #[derive(Debug)]
 pub struct BatchRequestMiddleware<M> {
     inner: M,
-    relay: Relay,
+    relay: Arc<Relay<HttpTransport>>,
+    queue: Arc<Mutex<Vec<QueuedRequest>>>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+    // Aborted on `Drop` so the background flush loop does not outlive the middleware.
+    flush_task: tokio::task::JoinHandle<()>,
 }
 
 impl<M: Middleware> BatchRequestMiddleware<M> {
     /// Initialize a new BatchRequest middleware.
     pub fn new(inner: M, relay_url: impl Into<Url>) -> Self {
-        Self {
-            inner,
-            relay: Relay::new(relay_url),
-        }
+        Self::with_batch_config(inner, relay_url, DEFAULT_MAX_BATCH_SIZE, DEFAULT_FLUSH_INTERVAL)
+    }
+
+    /// Initialize a new BatchRequest middleware with a custom `max_batch_size` and
+    /// `flush_interval`.
+    pub fn with_batch_config(
+        inner: M,
+        relay_url: impl Into<Url>,
+        max_batch_size: usize,
+        flush_interval: Duration,
+    ) -> Self {
+        let relay = Arc::new(Relay::new(relay_url));
+        let queue: Arc<Mutex<Vec<QueuedRequest>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let flush_relay = relay.clone();
+        let flush_queue = queue.clone();
+        let flush_task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            loop {
+                interval.tick().await;
+                flush(&flush_relay, &flush_queue).await;
+            }
+        });
+
+        Self { inner, relay, queue, max_batch_size, flush_interval, flush_task }
+    }
+
+    /// Signs every subsequent request sent through this middleware's relay, for authenticated
+    /// relays such as Flashbots.
+    pub fn with_signer(mut self, signer: LocalWallet) -> Self {
+        self.relay = Arc::new((*self.relay).clone().with_signer(signer));
+
+        // Restart the background flush loop on the signed relay, so timer flushes are signed too.
+        self.flush_task.abort();
+        let (flush_relay, flush_queue) = (self.relay.clone(), self.queue.clone());
+        let flush_interval = self.flush_interval;
+        self.flush_task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            loop {
+                interval.tick().await;
+                flush(&flush_relay, &flush_queue).await;
+            }
+        });
+        self
     }
 
     /// Get the relay client used by the middleware.
-    pub fn relay(&self) -> &Relay {
+    pub fn relay(&self) -> &Relay<HttpTransport> {
         &self.relay
     }
 
@@ -107,4 +186,110 @@ impl<M: Middleware> BatchRequestMiddleware<M> {
 
         Ok(response)
     }
-}
\ No newline at end of file
+
+    /// Queues `method`/`params` for the next batch and returns its raw, not yet deserialized,
+    /// response once that batch has been flushed.
+    async fn enqueue(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Box<RawValue>, BatchRequestMiddlewareError<M>> {
+        let (sender, receiver) = oneshot::channel();
+        let should_flush_now = {
+            let mut queue = self.queue.lock().await;
+            queue.push(QueuedRequest { method: method.to_string(), params, sender });
+            queue.len() >= self.max_batch_size
+        };
+
+        if should_flush_now {
+            flush(&self.relay, &self.queue).await;
+        }
+
+        receiver
+            .await
+            .map_err(|_| BatchRequestMiddlewareError::ChannelClosed)?
+            .map_err(|err| BatchRequestMiddlewareError::RelayError(RelayError::JsonRpcError(err)))
+    }
+}
+
+impl<M> Drop for BatchRequestMiddleware<M> {
+    /// Stops the background flush loop so it does not keep waking up, holding the last `relay`
+    /// and `queue` `Arc`s alive, for the remainder of the process.
+    fn drop(&mut self) {
+        self.flush_task.abort();
+    }
+}
+
+/// Drains `queue`, if non-empty, into a single batch sent through `relay`, then fans the
+/// responses back out to the senders matching each one to the `BatchHandle` its request was
+/// assigned, rather than relying on the order responses happen to come back in.
+async fn flush(relay: &Relay<HttpTransport>, queue: &Mutex<Vec<QueuedRequest>>) {
+    let drained = {
+        let mut queue = queue.lock().await;
+        if queue.is_empty() {
+            return;
+        }
+        std::mem::take(&mut *queue)
+    };
+
+    let mut batch = BatchRequest::with_capacity(drained.len());
+    let mut senders = Vec::with_capacity(drained.len());
+    for queued in drained {
+        match batch.add_request::<_, Value>(&queued.method, queued.params) {
+            Ok(handle) => senders.push((handle, queued.sender)),
+            Err(err) => {
+                let _ = queued.sender.send(Err(to_json_rpc_error(err)));
+            }
+        }
+    }
+
+    match relay.execute_batch(&mut batch).await {
+        Ok(responses) => {
+            for (handle, sender) in senders {
+                let response = responses
+                    .get_raw(&handle)
+                    .unwrap_or_else(|| Err(to_json_rpc_error(BatchError::EmptyBatch)));
+                let _ = sender.send(response);
+            }
+        }
+        Err(err) => {
+            let error = to_json_rpc_error(err);
+            for (_, sender) in senders {
+                let _ = sender.send(Err(error.clone()));
+            }
+        }
+    }
+}
+
+/// Wraps a transport-level failure as a `JsonRpcError` so it can travel through the same oneshot
+/// channel as an ordinary RPC error.
+fn to_json_rpc_error(err: impl fmt::Display) -> JsonRpcError {
+    JsonRpcError { code: -32000, message: err.to_string(), data: None }
+}
+
+#[async_trait]
+impl<M: Middleware> Middleware for BatchRequestMiddleware<M> {
+    type Error = BatchRequestMiddlewareError<M>;
+    type Provider = M::Provider;
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    /// Queues this request to be coalesced into the next outgoing batch, instead of hitting the
+    /// network immediately.
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: Serialize + DeserializeOwned + fmt::Debug + Send,
+    {
+        let params = serde_json::to_value(params)
+            .map_err(|err| BatchRequestMiddlewareError::RelayError(RelayError::BatchError(err.into())))?;
+
+        let raw = self.enqueue(method, params).await?;
+
+        serde_json::from_str(raw.get())
+            .map_err(|err| BatchRequestMiddlewareError::RelayError(RelayError::BatchError(err.into())))
+    }
+}
@@ -1,7 +1,12 @@
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use reqwest::{Client, Error as ReqwestError};
+use ethers::{
+    signers::{LocalWallet, Signer, WalletError},
+    types::H256,
+    utils::keccak256,
+};
+use reqwest::Error as ReqwestError;
 use serde::{de::DeserializeOwned, Serialize};
 use thiserror::Error;
 use url::Url;
@@ -10,18 +15,21 @@ use crate::{
     jsonrpc::{JsonRpcError, Request, Response},
 };
 use crate::batch::{BatchError, BatchRequest, BatchResponse};
+use crate::bundle::{BundleRequest, CallBundleResponse, SendBundleResponse};
+use crate::transport::{HttpTransport, Transport};
 
 #[derive(Debug)]
-pub struct Relay {
+pub struct Relay<T = HttpTransport> {
     id: AtomicU64,
-    client: Client,
-    url: Url,
+    transport: T,
+    // The searcher identity used to sign requests for authenticated relays, e.g. Flashbots.
+    signer: Option<LocalWallet>,
 }
 
 #[derive(Error, Debug)]
-/// Error thrown when sending an HTTP request
+/// Error thrown when sending a request through a `Relay`.
 pub enum RelayError {
-    /// Thrown if the request failed
+    /// Thrown if the underlying HTTP request failed
     #[error(transparent)]
     ReqwestError(#[from] ReqwestError),
 
@@ -36,37 +44,84 @@ pub enum RelayError {
     /// Thrown if sending an empty batch of JSON-RPC requests.
     #[error(transparent)]
     BatchError(#[from] BatchError),
+
+    /// Thrown by a `Transport` when the underlying connection could not be reached or was
+    /// closed before a response came back.
+    #[error("the transport connection was closed")]
+    TransportClosed,
+
+    /// Thrown if the underlying IO for a socket-based transport failed.
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    /// Thrown if a request could not be serialized to JSON.
+    #[error("failed to serialize request: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    /// Thrown if a WebSocket-based transport failed.
+    #[error(transparent)]
+    WsError(#[from] tokio_tungstenite::tungstenite::Error),
+
+    /// Thrown if signing a request for an authenticated relay failed.
+    #[error(transparent)]
+    SignerError(#[from] WalletError),
 }
 
 
-impl Relay {
-    /// Initializes a new relay client.
+impl Relay<HttpTransport> {
+    /// Initializes a new relay client backed by the default HTTP transport.
     pub fn new(url: impl Into<Url>) -> Self {
-        Self {
-            id: AtomicU64::new(0),
-            client: Client::new(),
-            url: url.into(),
-        }
+        Self::with_transport(HttpTransport::new(url))
     }
+}
 
+impl<T: Transport> Relay<T> {
+    /// Initializes a new relay client backed by the given `transport`, e.g. a `WsTransport` or an
+    /// `IpcTransport`.
+    pub fn with_transport(transport: T) -> Self {
+        Self { id: AtomicU64::new(0), transport, signer: None }
+    }
 
-    async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+    /// Signs every subsequent request with `signer`, attaching an `X-Flashbots-Signature` header
+    /// to the ones sent over HTTP so the relay can recover the searcher's identity.
+    pub fn with_signer(mut self, signer: LocalWallet) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Computes the `X-Flashbots-Signature` header value for `body`, if this relay has a signer
+    /// configured.
+    ///
+    /// The signature is the EIP-191 personal-sign of the hex-encoded keccak256 hash of `body`,
+    /// as expected by Flashbots-style relays.
+    async fn flashbots_signature(&self, body: &[u8]) -> Result<Option<String>, RelayError> {
+        let Some(signer) = &self.signer else {
+            return Ok(None);
+        };
+
+        let hash = H256::from(keccak256(body));
+        let signature = signer.sign_message(format!("{hash:?}")).await?;
+
+        Ok(Some(format!("{:?}:0x{signature}", signer.address())))
+    }
+
+    async fn request<P: Serialize + Send + Sync, R: DeserializeOwned>(
         &self,
         method: &str,
-        params: T,
+        params: P,
     ) -> Result<R, RelayError> {
         let next_id = self.id.fetch_add(1, Ordering::SeqCst);
-        let payload = Request::new(next_id, method, params);
+        let payload = serde_json::to_vec(&Request::new(next_id, method, params))?;
+        let signature = self.flashbots_signature(&payload).await?;
 
-        let res = self.client.post(self.url.as_ref()).json(&payload).send().await?;
-        let text = res.text().await?;
+        let text = self.transport.send_raw(&payload, signature.as_deref()).await?;
 
         let raw = match serde_json::from_str(&text) {
-            Ok(Response::Success { result, .. }) => result.to_owned(),
+            Ok(Response::Success { result, .. }) => result,
             Ok(Response::Error { error, .. }) => return Err(error.into()),
-            Ok(_) => {
+            Ok(Response::Notification { .. }) => {
                 let err = RelayError::SerdeJson {
-                    err: serde::de::Error::custom("unexpected notification over HTTP transport"),
+                    err: serde::de::Error::custom("unexpected notification over the transport"),
                     text,
                 };
                 return Err(err);
@@ -97,18 +152,30 @@ impl Relay {
         // Ids in the batch will start from next_id.
         batch.set_ids(next_id)?;
 
-        let res = self.client.post(self.url.as_ref()).json(batch.requests()?).send().await?;
-        let text = res.text().await?;
+        let payload = serde_json::to_vec(batch.requests()?)?;
+        let signature = self.flashbots_signature(&payload).await?;
+        let text = self.transport.send_raw(&payload, signature.as_deref()).await?;
 
-        // Get the responses for the batch.
-        let responses = serde_json::from_str::<Vec<Response>>(&text)
-            .map_err(|err| RelayError::SerdeJson { err, text: text.to_string() })?;
+        // Parsed lazily: `BatchResponse` only slices out each result, it does not deserialize it.
+        Ok(BatchResponse::new(text, batch.base_id())?)
+    }
+
+    /// Submits `bundle` via `eth_sendBundle`, returning the bundle's hash.
+    ///
+    /// Authenticated relays, such as Flashbots, require a signer; see
+    /// [`with_signer`](Self::with_signer).
+    pub async fn send_bundle(&self, bundle: &BundleRequest) -> Result<H256, RelayError> {
+        let res: SendBundleResponse = self.request("eth_sendBundle", [bundle]).await?;
+        Ok(res.bundle_hash)
+    }
 
-        Ok(BatchResponse::new(responses))
+    /// Simulates `bundle` via `eth_callBundle` against the current chain state.
+    pub async fn call_bundle(&self, bundle: &BundleRequest) -> Result<CallBundleResponse, RelayError> {
+        self.request("eth_callBundle", [bundle]).await
     }
 }
 
-impl FromStr for Relay {
+impl FromStr for Relay<HttpTransport> {
     type Err = url::ParseError;
 
     fn from_str(src: &str) -> Result<Self, Self::Err> {
@@ -117,8 +184,15 @@ impl FromStr for Relay {
     }
 }
 
-impl Clone for Relay {
+impl Clone for Relay<HttpTransport> {
     fn clone(&self) -> Self {
-        Self { id: AtomicU64::new(1), client: self.client.clone(), url: self.url.clone() }
+        // Starts the clone's counter at this relay's current value rather than restarting it, so
+        // the clone does not reuse ids this relay has already handed out; the two counters
+        // advance independently afterwards.
+        Self {
+            id: AtomicU64::new(self.id.load(Ordering::SeqCst)),
+            transport: self.transport.clone(),
+            signer: self.signer.clone(),
+        }
     }
 }
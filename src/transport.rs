@@ -0,0 +1,200 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Client;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+    sync::{mpsc, oneshot, Mutex},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use url::Url;
+
+use crate::relay::RelayError;
+
+/// Abstracts over the underlying connection a [`Relay`](crate::relay::Relay) sends its JSON-RPC
+/// payloads over.
+///
+/// A `send_raw` call submits one already-serialized JSON-RPC payload (a single request or a
+/// batch) and resolves to the raw, not yet deserialized, response body.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends `payload` and returns the raw response body.
+    ///
+    /// `flashbots_signature`, when set, is the value of the `X-Flashbots-Signature` header the
+    /// relay expects for authenticated endpoints (see [`Relay::with_signer`](crate::relay::Relay::with_signer)).
+    /// Transports that have no notion of headers, such as `WsTransport` and `IpcTransport`, ignore it.
+    async fn send_raw(&self, payload: &[u8], flashbots_signature: Option<&str>) -> Result<String, RelayError>;
+}
+
+/// Sends requests as HTTP POSTs, one per `send_raw` call. This is the default transport.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: Client,
+    url: Url,
+}
+
+impl HttpTransport {
+    /// Creates a new HTTP transport pointed at `url`.
+    pub fn new(url: impl Into<Url>) -> Self {
+        Self { client: Client::new(), url: url.into() }
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send_raw(&self, payload: &[u8], flashbots_signature: Option<&str>) -> Result<String, RelayError> {
+        let mut request = self
+            .client
+            .post(self.url.as_ref())
+            .header("content-type", "application/json")
+            .body(payload.to_vec());
+
+        if let Some(signature) = flashbots_signature {
+            request = request.header("X-Flashbots-Signature", signature);
+        }
+
+        let res = request.send().await?;
+
+        Ok(res.text().await?)
+    }
+}
+
+/// Extracts the lowest `id` present in a JSON-RPC payload, be it a single request/response object
+/// or a batch array of them.
+fn lowest_id(payload: &[u8]) -> Option<u64> {
+    let value: Value = serde_json::from_slice(payload).ok()?;
+    match value {
+        Value::Array(items) => items.iter().filter_map(|item| item.get("id")?.as_u64()).min(),
+        Value::Object(_) => value.get("id")?.as_u64(),
+        _ => None,
+    }
+}
+
+/// Map from the lowest request id of an in-flight `send_raw` call to the channel its matching
+/// response body should be delivered to.
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<String>>>>;
+
+/// Sends requests over a single, multiplexed WebSocket connection, routing each incoming message
+/// back to the `send_raw` call whose outgoing payload shares its lowest JSON-RPC id.
+#[derive(Debug)]
+pub struct WsTransport {
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: Pending,
+}
+
+impl WsTransport {
+    /// Connects to `url` and spawns the background tasks that drive the connection.
+    pub async fn connect(url: impl Into<Url>) -> Result<Self, RelayError> {
+        let (stream, _) = connect_async(url.into()).await?;
+        let (mut sink, mut source) = stream.split();
+
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let (outgoing, mut to_send) = mpsc::unbounded_channel::<Message>();
+
+        tokio::spawn(async move {
+            while let Some(message) = to_send.recv().await {
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let pending_reader = pending.clone();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = source.next().await {
+                let Message::Text(text) = message else { continue };
+                if let Some(id) = lowest_id(text.as_bytes()) {
+                    if let Some(sender) = pending_reader.lock().await.remove(&id) {
+                        let _ = sender.send(text);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { outgoing, pending })
+    }
+}
+
+#[async_trait]
+impl Transport for WsTransport {
+    async fn send_raw(&self, payload: &[u8], _flashbots_signature: Option<&str>) -> Result<String, RelayError> {
+        let id = lowest_id(payload).ok_or(RelayError::TransportClosed)?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().await.insert(id, sender);
+
+        let text = String::from_utf8_lossy(payload).into_owned();
+        self.outgoing.send(Message::Text(text)).map_err(|_| RelayError::TransportClosed)?;
+
+        receiver.await.map_err(|_| RelayError::TransportClosed)
+    }
+}
+
+/// Sends requests over a Unix domain socket, framing each payload as a single newline-delimited
+/// JSON message, the same way ethers-rs' own IPC transport talks to a local node.
+///
+/// Windows named pipe support is not implemented yet; this transport is Unix-only.
+#[derive(Debug)]
+pub struct IpcTransport {
+    outgoing: mpsc::UnboundedSender<Vec<u8>>,
+    pending: Pending,
+}
+
+impl IpcTransport {
+    /// Connects to the Unix domain socket at `path` and spawns the background tasks that drive
+    /// the connection.
+    pub async fn connect(path: impl AsRef<Path>) -> Result<Self, RelayError> {
+        let stream = UnixStream::connect(path.as_ref()).await?;
+        let (read_half, mut write_half) = stream.into_split();
+        let mut reader = BufReader::new(read_half);
+
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let (outgoing, mut to_send) = mpsc::unbounded_channel::<Vec<u8>>();
+
+        tokio::spawn(async move {
+            while let Some(mut payload) = to_send.recv().await {
+                payload.push(b'\n');
+                if write_half.write_all(&payload).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let pending_reader = pending.clone();
+        tokio::spawn(async move {
+            let mut line = String::new();
+            loop {
+                line.clear();
+                match reader.read_line(&mut line).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if let Some(id) = lowest_id(line.as_bytes()) {
+                            if let Some(sender) = pending_reader.lock().await.remove(&id) {
+                                let _ = sender.send(std::mem::take(&mut line));
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { outgoing, pending })
+    }
+}
+
+#[async_trait]
+impl Transport for IpcTransport {
+    async fn send_raw(&self, payload: &[u8], _flashbots_signature: Option<&str>) -> Result<String, RelayError> {
+        let id = lowest_id(payload).ok_or(RelayError::TransportClosed)?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().await.insert(id, sender);
+
+        self.outgoing.send(payload.to_vec()).map_err(|_| RelayError::TransportClosed)?;
+
+        receiver.await.map_err(|_| RelayError::TransportClosed)
+    }
+}